@@ -0,0 +1,276 @@
+//! Bit-level packing and table-driven decoding for a [`CanonicalCode`].
+//!
+//! [`BitWriter`] packs each symbol's canonical `(code_bits, len)` code
+//! word into a byte buffer. [`DecodeTable`] precomputes a flat lookup
+//! table indexed by the next `max_len` peeked bits so decoding a
+//! symbol is a single table read (falling back to a secondary table
+//! only when `max_len` exceeds the chosen root-table width), and
+//! [`BitReader`] walks a byte buffer bit by bit to drive it.
+
+use std::cmp;
+
+use super::CanonicalCode;
+
+/// Bit order used when packing/unpacking code words into bytes. The
+/// order of bits *within* a code word (most-significant bit of the
+/// code first) is the same either way; this only controls which end
+/// of each byte the bits are packed into.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum BitOrder {
+    /// Bits are packed starting at a byte's most-significant bit
+    /// (the DEFLATE/JPEG convention for Huffman code words).
+    Msb0,
+    /// Bits are packed starting at a byte's least-significant bit.
+    Lsb0,
+}
+
+/// Packs canonical code words into a byte buffer, MSB-of-the-code
+/// first, using the chosen [`BitOrder`] to lay bits out within each
+/// byte.
+pub struct BitWriter {
+    order: BitOrder,
+    buf: Vec<u8>,
+    cur: u8,
+    nbits: u32,
+}
+
+impl BitWriter {
+    /// Creates an empty writer using the given bit order.
+    pub fn new(order: BitOrder) -> BitWriter {
+        BitWriter { order, buf: Vec::new(), cur: 0, nbits: 0 }
+    }
+
+    /// Appends the low `len` bits of `bits`, most significant bit
+    /// first.
+    pub fn write_bits(&mut self, bits: u32, len: u32) {
+        for i in (0..len).rev() {
+            let bit = ((bits >> i) & 1) as u8;
+            match self.order {
+                BitOrder::Msb0 => self.cur |= bit << (7 - self.nbits),
+                BitOrder::Lsb0 => self.cur |= bit << self.nbits,
+            }
+            self.nbits += 1;
+            if self.nbits == 8 {
+                self.buf.push(self.cur);
+                self.cur = 0;
+                self.nbits = 0;
+            }
+        }
+    }
+
+    /// Writes the canonical code word assigned to `symbol` by `code`.
+    pub fn write_symbol(&mut self, code: &CanonicalCode, symbol: usize) {
+        let (bits, len) = code.code(symbol);
+        self.write_bits(bits, len);
+    }
+
+    /// Flushes any partial trailing byte (zero-padded) and returns the
+    /// packed bytes.
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.nbits > 0 {
+            self.buf.push(self.cur);
+        }
+        self.buf
+    }
+}
+
+/// Reads individual bits back out of a byte buffer in the given
+/// [`BitOrder`], for [`DecodeTable`] to peek and consume.
+pub struct BitReader<'a> {
+    order: BitOrder,
+    data: &'a [u8],
+    bitpos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    /// Creates a reader positioned at the start of `data`.
+    pub fn new(data: &'a [u8], order: BitOrder) -> BitReader<'a> {
+        BitReader { order, data, bitpos: 0 }
+    }
+
+    fn bit_at(&self, i: usize) -> u32 {
+        let byte_idx = i / 8;
+        if byte_idx >= self.data.len() {
+            return 0;
+        }
+        let bit_idx = i % 8;
+        let byte = self.data[byte_idx];
+        match self.order {
+            BitOrder::Msb0 => ((byte >> (7 - bit_idx)) & 1) as u32,
+            BitOrder::Lsb0 => ((byte >> bit_idx) & 1) as u32,
+        }
+    }
+
+    /// Peeks the next `n` bits, most significant bit first, without
+    /// consuming them. Bits past the end of the buffer read as zero.
+    pub fn peek(&self, n: u32) -> u32 {
+        let mut v = 0u32;
+        for k in 0..n {
+            v = (v << 1) | self.bit_at(self.bitpos + k as usize);
+        }
+        v
+    }
+
+    /// Consumes the next `n` bits.
+    pub fn advance(&mut self, n: u32) {
+        self.bitpos += n as usize;
+    }
+
+    /// The total number of bits consumed so far.
+    pub fn bits_consumed(&self) -> usize {
+        self.bitpos
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Entry {
+    /// A fully decoded symbol and the number of bits to consume for it:
+    /// in the root table the true length of its code word, in a
+    /// secondary table the length left after the root bits.
+    Symbol { symbol: u32, len: u32 },
+    /// The code word is longer than the root table width; continue
+    /// decoding in `subs[index]`.
+    Sub { index: u32 },
+}
+
+/// Width of the root lookup table. Symbols whose code word fits
+/// within this many bits decode in a single table read; longer code
+/// words (only possible when a `CanonicalCode`'s `max_len` exceeds
+/// this) fall back to a secondary per-prefix table.
+const ROOT_BITS: u32 = 9;
+
+fn fill_direct(table: &mut [Entry], table_bits: u32, bits: u32, len: u32, symbol: u32) {
+    let shift = table_bits - len;
+    let base = (bits as usize) << shift;
+    for entry in &mut table[base..base + (1usize << shift)] {
+        *entry = Entry::Symbol { symbol, len };
+    }
+}
+
+/// A flat lookup table for decoding a [`CanonicalCode`] one symbol at
+/// a time: peek `root_bits` bits and the table read alone yields the
+/// symbol and its true length, except for code words longer than
+/// `root_bits`, which are resolved via a secondary table keyed by
+/// their remaining bits.
+pub struct DecodeTable {
+    order: BitOrder,
+    root_bits: u32,
+    root: Vec<Entry>,
+    sub_bits: u32,
+    subs: Vec<Vec<Entry>>,
+}
+
+impl DecodeTable {
+    /// Builds a decode table for `code`, to be used with bit streams
+    /// packed in `order`.
+    pub fn from_canonical(code: &CanonicalCode, order: BitOrder) -> DecodeTable {
+        let max_len = code.codes().iter().map(|&(_, len)| len).max().unwrap_or(0);
+        let root_bits = cmp::min(max_len, ROOT_BITS);
+        let sub_bits = max_len - root_bits;
+
+        let mut root = vec![Entry::Sub { index: 0 }; 1usize << root_bits];
+        for (symbol, &(bits, len)) in code.codes().iter().enumerate() {
+            if len <= root_bits {
+                fill_direct(&mut root, root_bits, bits, len, symbol as u32);
+            }
+        }
+
+        // (prefix, [(symbol, code_bits, len), ...]) per overflow group.
+        type Group = (u32, Vec<(usize, u32, u32)>);
+        let mut groups: Vec<Group> = Vec::new();
+        for (symbol, &(bits, len)) in code.codes().iter().enumerate() {
+            if len > root_bits {
+                let prefix = bits >> (len - root_bits);
+                match groups.iter().position(|&(p, _)| p == prefix) {
+                    Some(i) => groups[i].1.push((symbol, bits, len)),
+                    None => groups.push((prefix, vec![(symbol, bits, len)])),
+                }
+            }
+        }
+
+        let mut subs = Vec::with_capacity(groups.len());
+        for (prefix, syms) in groups {
+            let index = subs.len() as u32;
+            let mut sub_table = vec![Entry::Sub { index: 0 }; 1usize << sub_bits];
+            for (symbol, bits, len) in syms {
+                let suffix_len = len - root_bits;
+                let suffix_bits = bits & ((1u32 << suffix_len) - 1);
+                fill_direct(&mut sub_table, sub_bits, suffix_bits, suffix_len, symbol as u32);
+            }
+            subs.push(sub_table);
+            root[prefix as usize] = Entry::Sub { index };
+        }
+
+        DecodeTable { order, root_bits, root, sub_bits, subs }
+    }
+
+    /// Decodes one symbol from `reader`, advancing it past the symbol's
+    /// code word.
+    pub fn decode_symbol(&self, reader: &mut BitReader) -> u32 {
+        debug_assert_eq!(reader.order, self.order);
+        match self.root[reader.peek(self.root_bits) as usize] {
+            Entry::Symbol { symbol, len } => {
+                reader.advance(len);
+                symbol
+            }
+            Entry::Sub { index } => {
+                reader.advance(self.root_bits);
+                match self.subs[index as usize][reader.peek(self.sub_bits) as usize] {
+                    Entry::Symbol { symbol, len } => {
+                        reader.advance(len);
+                        symbol
+                    }
+                    Entry::Sub { .. } =>
+                        unreachable!("secondary decode tables never nest further"),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitOrder, BitReader, BitWriter, DecodeTable};
+    use CanonicalCode;
+
+    #[test]
+    fn round_trips_through_encode_and_decode() {
+        let lens = [2u32, 2, 3, 3, 3, 3];
+        let code = CanonicalCode::from_lengths(&lens).unwrap();
+        let symbols = [5usize, 0, 1, 2, 3, 4, 5, 0];
+
+        let mut writer = BitWriter::new(BitOrder::Msb0);
+        for &s in &symbols {
+            writer.write_symbol(&code, s);
+        }
+        let bytes = writer.finish();
+
+        let table = DecodeTable::from_canonical(&code, BitOrder::Msb0);
+        let mut reader = BitReader::new(&bytes, BitOrder::Msb0);
+        let decoded: Vec<u32> = symbols.iter().map(|_| table.decode_symbol(&mut reader)).collect();
+
+        let expected: Vec<u32> = symbols.iter().map(|&s| s as u32).collect();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn round_trips_with_overflow_table() {
+        // max_len (12) exceeds ROOT_BITS (9), forcing a secondary table.
+        let lens = [1u32, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 12];
+        let code = CanonicalCode::from_lengths(&lens).unwrap();
+        let symbols: Vec<usize> = (0..lens.len()).rev().collect();
+
+        let mut writer = BitWriter::new(BitOrder::Lsb0);
+        for &s in &symbols {
+            writer.write_symbol(&code, s);
+        }
+        let bytes = writer.finish();
+
+        let table = DecodeTable::from_canonical(&code, BitOrder::Lsb0);
+        let mut reader = BitReader::new(&bytes, BitOrder::Lsb0);
+        let decoded: Vec<u32> = symbols.iter().map(|_| table.decode_symbol(&mut reader)).collect();
+
+        let expected: Vec<u32> = symbols.iter().map(|&s| s as u32).collect();
+        assert_eq!(decoded, expected);
+    }
+}
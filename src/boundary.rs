@@ -0,0 +1,220 @@
+//! An alternative solver for optimal length-limited code word lengths
+//! using the boundary package-merge technique (Katajainen, Moffat &
+//! Turpin). Unlike [`package_merge_weights`](super::package_merge_weights),
+//! which materializes `O(n)`-sized `list`/`merged`/`flags` arrays for
+//! every one of the `max_len` levels, this runs in `O(n * max_len)`
+//! time using only `O(n)` live working memory: each of the `max_len`
+//! lists keeps just its two most recent chains, linked back to earlier
+//! lists through a reference-counted `tail` pointer so a chain is
+//! freed as soon as nothing still reachable from `recent` needs it,
+//! and the final lengths are read off by walking that chain once at
+//! the end.
+
+use std::ops::Add;
+use std::rc::Rc;
+
+use super::Error;
+
+struct Chain<W> {
+    weight: W,
+    count: u32,
+    tail: Option<Rc<Chain<W>>>,
+}
+
+/// For each of the `max_len` lists, the two most recent chains
+/// (oldest first). A chain stays alive only as long as some `recent`
+/// slot (at any level) still reaches it, directly or via `tail`; once
+/// superseded everywhere, its `Rc` drops it.
+struct Lists<W> {
+    recent: Vec<[Option<Rc<Chain<W>>>; 2]>,
+}
+
+impl<W: Copy> Lists<W> {
+    fn count(&self, chain: &Option<Rc<Chain<W>>>) -> usize {
+        chain.as_ref().map(|c| c.count as usize).unwrap_or(0)
+    }
+
+    fn weight(&self, chain: &Chain<W>) -> W {
+        chain.weight
+    }
+
+    fn num_chains(&self, level: usize) -> usize {
+        self.recent[level].iter().filter(|c| c.is_some()).count()
+    }
+
+    fn push(&mut self, level: usize, chain: Chain<W>) {
+        self.recent[level] = [self.recent[level][1].clone(), Some(Rc::new(chain))];
+    }
+}
+
+/// Appends one more chain to `list[level]`, choosing between the next
+/// unused leaf and a package of the two most recent chains of
+/// `list[level - 1]` (recursively making sure that list has two
+/// chains to offer first). Ties between a leaf and a package prefer
+/// the leaf, matching `package_merge_weights`.
+fn boundary<W>(lists: &mut Lists<W>, w: &[W], n: usize, level: usize)
+where W: Copy + Ord + Add<Output = W> {
+    if level == 0 {
+        let lastcount = lists.count(&lists.recent[0][1]);
+        if lastcount >= n {
+            return;
+        }
+        let tail = lists.recent[0][1].as_ref().and_then(|c| c.tail.clone());
+        lists.push(0, Chain { weight: w[lastcount], count: (lastcount + 1) as u32, tail });
+        return;
+    }
+
+    while lists.num_chains(level - 1) < 2 {
+        boundary(lists, w, n, level - 1);
+    }
+
+    let lastcount = lists.count(&lists.recent[level][1]);
+    let a = lists.recent[level - 1][0].clone().unwrap();
+    let b = lists.recent[level - 1][1].clone().unwrap();
+    let weightsum = lists.weight(&a) + lists.weight(&b);
+
+    if lastcount < n && weightsum >= w[lastcount] {
+        let tail = lists.recent[level][1].as_ref().and_then(|c| c.tail.clone());
+        lists.push(level, Chain { weight: w[lastcount], count: (lastcount + 1) as u32, tail });
+    } else {
+        lists.push(level, Chain { weight: weightsum, count: lastcount as u32, tail: Some(b) });
+        boundary(lists, w, n, level - 1);
+        boundary(lists, w, n, level - 1);
+    }
+}
+
+/// Computes optimal length-limited code word lengths for `weights`
+/// using boundary package-merge, as an `O(n)`-space alternative to
+/// [`package_merge_weights`](super::package_merge_weights) for large
+/// alphabets and large `max_len`. Returns the same lengths the latter
+/// would for the same input.
+pub fn package_merge_boundary<W>(weights: &[W], max_len: u32) -> Result<Vec<u32>, Error>
+where W: Copy + Ord + Add<Output = W> {
+    let n = weights.len();
+    if n == 0 {
+        return Err(Error::NoSymbols);
+    }
+    if n > (1usize << max_len) {
+        return Err(Error::MaxLenTooSmall);
+    }
+    if max_len > 32 {
+        return Err(Error::MaxLenTooLarge);
+    }
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&a, &b| weights[a].cmp(&weights[b]));
+    let w: Vec<W> = order.iter().map(|&i| weights[i]).collect();
+
+    let l = max_len as usize;
+    let mut lists = Lists { recent: vec![[None, None]; l] };
+
+    // Drive the top list until it holds 2n-2 chains: that's exactly
+    // how many leaf coins the optimal solution packs in at this depth.
+    for _ in 0..(2 * n - 2) {
+        boundary(&mut lists, &w, n, l - 1);
+    }
+
+    let mut code_lens = vec![0u32; n];
+    if l == 0 {
+        // Only a single symbol fits with max_len = 0; it needs no bits.
+        return Ok(code_lens);
+    }
+    let mut node = lists.recent[l - 1][1].clone();
+    while let Some(chain) = node {
+        let count = chain.count as usize;
+        for &symbol in &order[..count] {
+            code_lens[symbol] += 1;
+        }
+        node = chain.tail.clone();
+    }
+
+    Ok(code_lens)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::rc::Rc;
+
+    use super::{boundary, Chain, Lists};
+    use super::package_merge_boundary;
+    use super::super::package_merge_weights;
+
+    #[test]
+    fn matches_package_merge_weights() {
+        let weights: [u32; 7] = [1, 32, 16, 4, 8, 2, 1];
+        for &max_len in &[3, 5, 6, 8, 16] {
+            let expected = package_merge_weights(&weights, max_len).unwrap();
+            let actual = package_merge_boundary(&weights, max_len).unwrap();
+            assert_eq!(actual, expected, "max_len = {}", max_len);
+        }
+    }
+
+    #[test]
+    fn matches_for_larger_alphabets() {
+        let weights: Vec<u32> = (1..=40u32).map(|i| i * i).collect();
+        for &max_len in &[6, 8, 10, 32] {
+            let expected = package_merge_weights(&weights, max_len).unwrap();
+            let actual = package_merge_boundary(&weights, max_len).unwrap();
+            assert_eq!(actual, expected, "max_len = {}", max_len);
+        }
+    }
+
+    #[test]
+    fn single_symbol_needs_no_bits() {
+        let weights: [u32; 1] = [42];
+        let cl = package_merge_boundary(&weights, 8).unwrap();
+        assert_eq!(&cl[..], &[0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn it_fails() {
+        let weights: [u32; 7] = [1, 32, 16, 4, 8, 2, 1];
+        package_merge_boundary(&weights, 2).unwrap();
+    }
+
+    /// Counts chains still reachable from `recent`, following `tail`
+    /// links and deduplicating by `Rc` identity (superseded chains
+    /// whose last reference just dropped aren't reachable at all).
+    fn live_chain_count<W>(lists: &Lists<W>) -> usize {
+        let mut seen: HashSet<*const Chain<W>> = HashSet::new();
+        let mut stack: Vec<Rc<Chain<W>>> = lists.recent.iter()
+            .flat_map(|slot| slot.iter().flatten().cloned())
+            .collect();
+        while let Some(chain) = stack.pop() {
+            if seen.insert(Rc::as_ptr(&chain)) {
+                if let Some(tail) = &chain.tail {
+                    stack.push(tail.clone());
+                }
+            }
+        }
+        seen.len()
+    }
+
+    #[test]
+    fn live_chain_count_stays_on_not_on_times_max_len() {
+        // Drives `Lists` directly (same crate/module as `boundary`) so
+        // the live set can be inspected before it's discarded. Had
+        // `tail` still been a never-freed arena index, this would have
+        // held onto roughly `2 * n * max_len` chains instead.
+        let weights: Vec<u32> = (1..=200u32).map(|i| i * i).collect();
+        let n = weights.len();
+        for &max_len in &[8u32, 16, 32] {
+            let mut order: Vec<usize> = (0..n).collect();
+            order.sort_by(|&a, &b| weights[a].cmp(&weights[b]));
+            let w: Vec<u32> = order.iter().map(|&i| weights[i]).collect();
+
+            let l = max_len as usize;
+            let mut lists = Lists { recent: vec![[None, None]; l] };
+            for _ in 0..(2 * n - 2) {
+                boundary(&mut lists, &w, n, l - 1);
+            }
+
+            let live = live_chain_count(&lists);
+            assert!(live <= 8 * n,
+                "live chain count {} exceeds O(n) bound (n = {}, max_len = {})",
+                live, n, max_len);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Canonical code construction from the code word lengths computed by
+//! [`package_merge`](super::package_merge) /
+//! [`package_merge_weights`](super::package_merge_weights).
+
+use super::Error;
+
+/// A length-limited prefix-free code, assigning each symbol a
+/// canonical `(code_bits, len)` code word on top of lengths computed
+/// by the package-merge algorithm.
+///
+/// Codes are assigned in canonical order: symbols are sorted by code
+/// length and, within the same length, by symbol index. The shortest
+/// length in use starts at code `0`; moving to a longer length class
+/// increments the running code and shifts it left by the difference
+/// in length.
+#[derive(Clone, Debug)]
+pub struct CanonicalCode {
+    codes: Vec<(u32, u32)>,
+}
+
+impl CanonicalCode {
+    /// Builds a canonical code from per-symbol code word lengths, as
+    /// returned by `package_merge`/`package_merge_weights`.
+    ///
+    /// Returns `Error::NoSymbols` if `lens` is empty, and
+    /// `Error::IncompleteCode` if the lengths don't form a complete
+    /// prefix code.
+    pub fn from_lengths(lens: &[u32]) -> Result<CanonicalCode, Error> {
+        if lens.is_empty() {
+            return Err(Error::NoSymbols);
+        }
+
+        let max_len = *lens.iter().max().unwrap();
+        if max_len > 32 {
+            return Err(Error::MaxLenTooLarge);
+        }
+
+        let total = 1u64 << max_len;
+        let used: u64 = lens.iter().map(|&len| 1u64 << (max_len - len)).sum();
+        if used != total {
+            return Err(Error::IncompleteCode);
+        }
+
+        let mut order: Vec<usize> = (0..lens.len()).collect();
+        order.sort_by_key(|&i| (lens[i], i));
+
+        let mut codes = vec![(0u32, 0u32); lens.len()];
+        let mut code: u32 = 0;
+        let mut prev_len: u32 = lens[order[0]];
+        for &i in &order {
+            let len = lens[i];
+            if len > prev_len {
+                code <<= len - prev_len;
+                prev_len = len;
+            }
+            codes[i] = (code, len);
+            code += 1;
+        }
+
+        Ok(CanonicalCode { codes })
+    }
+
+    /// The number of symbols in this code.
+    pub fn len(&self) -> usize {
+        self.codes.len()
+    }
+
+    /// Whether this code has no symbols. `from_lengths` never
+    /// produces one, since an empty `lens` is rejected.
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+
+    /// Returns the `(code_bits, len)` code word assigned to `symbol`.
+    pub fn code(&self, symbol: usize) -> (u32, u32) {
+        self.codes[symbol]
+    }
+
+    /// All `(code_bits, len)` code words, indexed by symbol.
+    pub fn codes(&self) -> &[(u32, u32)] {
+        &self.codes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CanonicalCode;
+    use super::Error;
+
+    #[test]
+    fn it_works() {
+        // 2 symbols of length 2, 4 symbols of length 3: complete code.
+        let lens = [2, 2, 3, 3, 3, 3];
+        let cc = CanonicalCode::from_lengths(&lens).unwrap();
+        assert_eq!(cc.code(0), (0b00, 2));
+        assert_eq!(cc.code(1), (0b01, 2));
+        assert_eq!(cc.code(2), (0b100, 3));
+        assert_eq!(cc.code(3), (0b101, 3));
+        assert_eq!(cc.code(4), (0b110, 3));
+        assert_eq!(cc.code(5), (0b111, 3));
+    }
+
+    #[test]
+    fn rejects_incomplete_code() {
+        let lens = [1, 2]; // Kraft sum 1/2 + 1/4 != 1
+        assert_eq!(CanonicalCode::from_lengths(&lens).unwrap_err(), Error::IncompleteCode);
+    }
+
+    #[test]
+    fn rejects_oversubscribed_code() {
+        let lens = [1, 1, 1]; // Kraft sum 3/2 != 1
+        assert_eq!(CanonicalCode::from_lengths(&lens).unwrap_err(), Error::IncompleteCode);
+    }
+}
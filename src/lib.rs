@@ -8,13 +8,24 @@
 
 extern crate itertools;
 
+mod bits;
+mod boundary;
+mod canonical;
+mod mergeiter;
+
 use std::cmp;
 use std::error;
 use std::fmt;
 use std::mem;
+use std::ops::Add;
 
 use itertools::Itertools;
 
+pub use bits::{BitOrder, BitReader, BitWriter, DecodeTable};
+pub use boundary::package_merge_boundary;
+pub use canonical::CanonicalCode;
+pub use mergeiter::{merge, Either, MergeIter, Pick};
+
 use Error::*;
 
 fn order_non_nan(a: f64, b: f64) -> cmp::Ordering {
@@ -23,12 +34,38 @@ fn order_non_nan(a: f64, b: f64) -> cmp::Ordering {
     { cmp::Ordering::Equal }
 }
 
-fn complete_chunks<T>(mut slice: &[T], csize: usize) -> std::slice::Chunks<T> {
-    let remainder = slice.len() % csize;
-    if remainder > 0 {
-        slice = &slice[0..(slice.len() - remainder)];
+/// Wraps `f64` so it can be used wherever `Ord` weights are expected,
+/// using the same NaN-as-equal ordering as the old `order_non_nan`
+/// helper. This is only used internally to let [`package_merge`]
+/// delegate to [`package_merge_weights`].
+#[derive(Copy, Clone, Debug)]
+struct OrderedF64(f64);
+
+impl PartialEq for OrderedF64 {
+    fn eq(&self, other: &Self) -> bool {
+        order_non_nan(self.0, other.0) == cmp::Ordering::Equal
+    }
+}
+
+impl Eq for OrderedF64 {}
+
+impl PartialOrd for OrderedF64 {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedF64 {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        order_non_nan(self.0, other.0)
+    }
+}
+
+impl Add for OrderedF64 {
+    type Output = OrderedF64;
+    fn add(self, other: Self) -> Self::Output {
+        OrderedF64(self.0 + other.0)
     }
-    slice.chunks(csize)
 }
 
 /// The error type for the package-merge algorithm
@@ -40,6 +77,10 @@ pub enum Error {
     MaxLenTooSmall,
     /// The given `max_len` constraint was too large.
     MaxLenTooLarge,
+    /// The given code lengths don't form a complete prefix code, i.e.
+    /// the Kraft–McMillan sum of `2^(-len)` over all symbols isn't
+    /// exactly `1`.
+    IncompleteCode,
 }
 
 impl Error {
@@ -51,6 +92,8 @@ impl Error {
                 "package-merge error: max_len parameter was chosen too small",
             MaxLenTooLarge =>
                 "package-merge error: max_len parameter was chosen too large",
+            IncompleteCode =>
+                "package-merge error: code lengths don't form a complete prefix code",
         }
     }
 }
@@ -67,16 +110,24 @@ impl error::Error for Error {
     }
 }
 
-/// Given all symbol frequencies (or probabilities) and a limit on the
-/// maximum length of code words (up to 32), this function will apply
-/// the package merge algorithm to compute optimal code word lengths
-/// for the symbols so that the expected code word length is minimized.
-pub fn package_merge(frequencies: &[f64], max_len: u32) -> Result<Vec<u32>, Error> {
-
-    if frequencies.is_empty() {
+/// Given all symbol weights (integer counts, exact fractions, ...) and
+/// a limit on the maximum length of code words (up to 32), this
+/// function will apply the package merge algorithm to compute optimal
+/// code word lengths for the symbols so that the expected code word
+/// length is minimized.
+///
+/// Unlike [`package_merge`], this works with any `W` that can be
+/// ordered and summed exactly, so passing `u32`/`u64` symbol counts
+/// gives deterministic results with no floating-point comparison
+/// hazards (NaN, accumulated rounding error from repeatedly summing
+/// `s[0] + s[1]` across up to 32 levels).
+pub fn package_merge_weights<W>(weights: &[W], max_len: u32) -> Result<Vec<u32>, Error>
+where W: Copy + Ord + Add<Output = W> {
+
+    if weights.is_empty() {
         return Err(Error::NoSymbols);
     }
-    if frequencies.len() > (1usize << max_len) {
+    if weights.len() > (1usize << max_len) {
         return Err(Error::MaxLenTooSmall);
     }
     if max_len > 32 {
@@ -85,22 +136,22 @@ pub fn package_merge(frequencies: &[f64], max_len: u32) -> Result<Vec<u32>, Erro
 
     let sorted = {
         let mut tmp = Vec::new();
-        tmp.extend(0..frequencies.len());
-        tmp.sort_by( |&a, &b| order_non_nan(frequencies[a],frequencies[b]) );
+        tmp.extend(0..weights.len());
+        tmp.sort_by( |&a, &b| weights[a].cmp(&weights[b]) );
         tmp
     };
 
-    let capa = frequencies.len() * 2 - 1;
-    let mut list: Vec<f64> = Vec::with_capacity(capa);
+    let capa = weights.len() * 2 - 1;
+    let mut list: Vec<W> = Vec::with_capacity(capa);
     let mut flags: Vec<u32> = vec![0; capa];
-    let mut merged: Vec<f64> = Vec::with_capacity(capa);
+    let mut merged: Vec<W> = Vec::with_capacity(capa);
 
     for depth in 0..max_len {
         {
             merged.clear();
             let mask = 1u32 << depth;
-            let pairs = complete_chunks(&list, 2).map( |s| (s[0] + s[1], true) );
-            let srted = sorted.iter().map( |&i| (frequencies[i], false) );
+            let pairs = list.chunks_exact(2).map( |s| (s[0] + s[1], true) );
+            let srted = sorted.iter().map( |&i| (weights[i], false) );
             for (p, m) in pairs.merge_by(srted, |a, b| a.0 < b.0 ) {
                 if m { // was this a merged item?
                     flags[merged.len()] |= mask;
@@ -111,9 +162,9 @@ pub fn package_merge(frequencies: &[f64], max_len: u32) -> Result<Vec<u32>, Erro
         mem::swap(&mut merged, &mut list);
     }
 
-    let mut n = frequencies.len() * 2 - 2;
+    let mut n = weights.len() * 2 - 2;
     debug_assert!(list.len() >= n);
-    let mut code_lens = vec![0u32; frequencies.len()];
+    let mut code_lens = vec![0u32; weights.len()];
     let mut depth = max_len;
     while depth > 0 && n > 0 {
         depth -= 1;
@@ -132,9 +183,23 @@ pub fn package_merge(frequencies: &[f64], max_len: u32) -> Result<Vec<u32>, Erro
     Ok(code_lens)
 }
 
+/// Given all symbol frequencies (or probabilities) and a limit on the
+/// maximum length of code words (up to 32), this function will apply
+/// the package merge algorithm to compute optimal code word lengths
+/// for the symbols so that the expected code word length is minimized.
+///
+/// This is a thin wrapper around [`package_merge_weights`] for callers
+/// who only have floating-point frequencies; it preserves the previous
+/// NaN-as-equal ordering behavior. Prefer `package_merge_weights` with
+/// exact integer counts when possible.
+pub fn package_merge(frequencies: &[f64], max_len: u32) -> Result<Vec<u32>, Error> {
+    let weights: Vec<OrderedF64> = frequencies.iter().cloned().map(OrderedF64).collect();
+    package_merge_weights(&weights, max_len)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::package_merge;
+    use super::{package_merge, package_merge_weights};
 
     #[test]
     fn it_works() {
@@ -151,5 +216,14 @@ mod tests {
         let freqs = [1.0, 32.0, 16.0, 4.0, 8.0, 2.0, 1.0];
         package_merge(&freqs, 2).unwrap();
     }
+
+    #[test]
+    fn weights_match_frequencies() {
+        let weights: [u32; 7] = [1, 32, 16, 4, 8, 2, 1];
+        let cl = package_merge_weights(&weights, 8).unwrap();
+        assert_eq!(&cl[..], &[6, 1, 2, 4, 3, 5, 6]);
+        let cl = package_merge_weights(&weights, 5).unwrap();
+        assert_eq!(&cl[..], &[5, 1, 2, 5, 3, 5, 5]);
+    }
 }
 
@@ -1,16 +1,36 @@
+//! A merge of two iterators driven by a caller-supplied comparator,
+//! tagging each yielded element with which side it came from.
+//!
+//! This is a standalone public utility, not currently used by
+//! [`package_merge_weights`](super::package_merge_weights) (which calls
+//! `itertools::merge_by` directly and doesn't need to know which input
+//! an element came from). Unlike `itertools::merge_by`,
+//! [`MergeIter`] keeps that information around as an [`Either`].
+
 use std::mem;
 
+/// Tags a value yielded by [`MergeIter`] with which of the two input
+/// iterators produced it.
 #[derive(Copy,Clone,PartialEq,Eq,Debug)]
 pub enum Either<T, U> {
+    /// The value came from the first (`A`) iterator.
     Left(T),
+    /// The value came from the second (`B`) iterator.
     Right(U),
 }
 
+/// Tells [`MergeIter`] which of the two pending elements to yield next.
 pub enum Pick {
+    /// Yield the pending element from the first (`A`) iterator.
     Left,
+    /// Yield the pending element from the second (`B`) iterator.
     Right,
 }
 
+/// An iterator that merges `A` and `B`, at each step asking a `C:
+/// FnMut(&A::Item, &B::Item) -> Pick` comparator which of the two
+/// pending elements to yield next. Once one side is exhausted, the
+/// rest of the other side is drained as-is. Build one with [`merge`].
 #[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
 pub struct MergeIter<A: Iterator, B: Iterator, C> {
     ita: A,
@@ -30,7 +50,7 @@ where C: FnMut(&A::Item, &B::Item) -> Pick {
             itb: ib,
             ela: ea,
             elb: eb,
-            pck: pck
+            pck,
         }
     }
 }
@@ -80,11 +100,27 @@ where C: FnMut(&A::Item, &B::Item) -> Pick {
     }
 }
 
+// `A`/`B` being `ExactSizeIterator` only guarantees that *their own*
+// `size_hint`/`len` agree; it says nothing about arithmetic done on
+// top of them. So this deliberately doesn't just derive `len` from
+// `Iterator::size_hint` (the default `ExactSizeIterator::len` would),
+// since that would silently inherit any inexactness introduced by a
+// future change to `size_hint` above. Going through `ita.len()` /
+// `itb.len()` directly ties this impl's exactness to the same
+// guarantee `A`/`B` already promise of themselves.
 impl<A: ExactSizeIterator, B: ExactSizeIterator, C> ExactSizeIterator
 for MergeIter<A, B, C> where C: FnMut(&A::Item, &B::Item) -> Pick {
+    fn len(&self) -> usize {
+        let mut add = 0;
+        if self.ela.is_some() { add += 1; }
+        if self.elb.is_some() { add += 1; }
+        self.ita.len() + self.itb.len() + add
+    }
 }
 
-
+/// Merges `a` and `b` into a single iterator of [`Either`], using
+/// `pick` to decide, whenever both sides still have a pending
+/// element, which one to yield next.
 pub fn merge<I1: Iterator, I2: Iterator, P>(a: I1, b: I2, pick: P) -> MergeIter<I1, I2, P>
 where P: FnMut(&I1::Item, &I2::Item) -> Pick {
     MergeIter::new(a, b, pick)
@@ -98,7 +134,7 @@ mod tests {
         if a < b { Pick::Left }
         else { Pick::Right }
     }
-    
+
     #[test]
     fn it_works() {
         let f1 = [1.25, 2.375, 5.5, 9.25];
@@ -115,5 +151,96 @@ mod tests {
             Either::Left(9.25)
         ]);
     }
-}
 
+    #[test]
+    fn exact_size_matches_actual_count() {
+        let f1 = [1.25, 2.375, 5.5, 9.25];
+        let f2 = [3.25, 3.375, 6.5, 7.75, 20.0];
+        let m = merge(f1.iter().cloned(), f2.iter().cloned(), pick_f64);
+        assert_eq!(m.len(), f1.len() + f2.len());
+        assert_eq!(m.len(), m.count());
+    }
+
+    /// A tiny xorshift PRNG, so the property tests below can run many
+    /// pseudo-random trials deterministically without an external
+    /// quickcheck-style dependency (none is available to this crate).
+    struct Rng(u64);
+
+    impl Rng {
+        fn next_usize(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % (bound as u64)) as usize
+        }
+    }
+
+    /// An `ExactSizeIterator` over `data` that reports a caller-chosen
+    /// `reported_len` instead of its true remaining count, standing in
+    /// for an `ExactSizeIterator` impl whose `len()` doesn't actually
+    /// track how many elements are left to yield (a contract
+    /// violation `ExactSizeIterator` itself can't rule out). Used
+    /// below to check that `MergeIter::len()` is exactly `a.len() +
+    /// b.len() + pending`, so any unsoundness is confined to what the
+    /// inputs themselves already introduced, not amplified by
+    /// `MergeIter`.
+    struct Dishonest<T> {
+        data: std::vec::IntoIter<T>,
+        reported_len: usize,
+    }
+
+    fn dishonest<T>(data: Vec<T>, reported_len: usize) -> Dishonest<T> {
+        Dishonest { data: data.into_iter(), reported_len }
+    }
+
+    impl<T> Iterator for Dishonest<T> {
+        type Item = T;
+
+        fn next(&mut self) -> Option<T> {
+            self.data.next()
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (self.reported_len, Some(self.reported_len))
+        }
+    }
+
+    impl<T> ExactSizeIterator for Dishonest<T> {
+        fn len(&self) -> usize {
+            self.reported_len
+        }
+    }
+
+    #[test]
+    fn len_matches_true_count_for_honest_exact_size_iterators() {
+        let mut rng = Rng(0x1234_5678_9abc_def0);
+        for _ in 0..200 {
+            let n1 = rng.next_usize(8);
+            let n2 = rng.next_usize(8);
+            let f1: Vec<f64> = (0..n1).map(|i| i as f64).collect();
+            let f2: Vec<f64> = (0..n2).map(|i| i as f64 + 0.5).collect();
+
+            let m = merge(f1.iter().cloned(), f2.iter().cloned(), pick_f64);
+            assert_eq!(m.len(), n1 + n2, "n1={} n2={}", n1, n2);
+            assert_eq!(m.len(), m.count(), "n1={} n2={}", n1, n2);
+        }
+    }
+
+    #[test]
+    fn len_is_sum_of_inputs_reported_lengths_even_when_they_lie() {
+        let mut rng = Rng(0x0fed_cba9_8765_4321);
+        for _ in 0..200 {
+            let n1 = rng.next_usize(6);
+            let n2 = rng.next_usize(6);
+            let lie_a = rng.next_usize(20);
+            let lie_b = rng.next_usize(20);
+            let f1: Vec<f64> = (0..n1).map(|i| i as f64).collect();
+            let f2: Vec<f64> = (0..n2).map(|i| i as f64 + 0.5).collect();
+            let add = (n1 > 0) as usize + (n2 > 0) as usize;
+
+            let m = merge(dishonest(f1, lie_a), dishonest(f2, lie_b), pick_f64);
+            assert_eq!(m.len(), lie_a + lie_b + add,
+                "n1={} n2={} lie_a={} lie_b={}", n1, n2, lie_a, lie_b);
+        }
+    }
+}